@@ -3,71 +3,141 @@
 /// This binary runs a server on a TCP socket that proxies messages to number
 /// worker tasks handle the messages and perform some action.
 
+use std::collections::HashMap;
 use std::thread;
 
-use iot_comm::core::Sensor;
+use iot_comm::core::auth::Connection;
+use iot_comm::core::discovery;
+use iot_comm::core::wire;
 
+mod metrics;
+use metrics::{InfluxConfig, Measurement};
+
+mod mqtt;
+use mqtt::{Bridge, MqttConfig};
+
+/// The shared HMAC key controllers must sign their frames with. Empty
+/// disables authentication, matching the unauthenticated behavior used for
+/// local testing.
+const SHARED_KEY: &[u8] = b"";
+
+/// The port the ROUTER frontend binds to, and what the server advertises
+/// over mDNS.
+const PORT: u16 = 5570;
 
 /// The main server task.
-/// 
+///
 /// This acts as a proxy layer that passes clients requests received over TCP
 /// to a number of sever workers tasks via IPC.
 fn server_task() {
     let context = zmq::Context::new();
     let frontend = context.socket(zmq::ROUTER).unwrap();
     frontend
-        .bind("tcp://*:5570")
+        .bind(&format!("tcp://*:{}", PORT))
         .expect("server failed binding frontend");
     let backend = context.socket(zmq::DEALER).unwrap();
     backend
         .bind("inproc://backend")
         .expect("server failed binding backend");
-    for _ in 0..5 {
+
+    // Advertise the ROUTER endpoint so controllers can find us without a
+    // hardcoded address. `*` only works as a bind address, so the endpoint
+    // we hand out has to be our actual reachable address, not the wildcard
+    // we bound above. The responder keeps re-announcing for as long as it's
+    // alive, so it's kept bound to this stack frame for the life of the
+    // server task.
+    let advertised_host = discovery::local_address();
+    let _responder = discovery::advertise(PORT, &format!("tcp://{}:{}", advertised_host, PORT));
+
+    let influx_ctx = context.clone();
+    thread::spawn(move || metrics::influx_writer_task(&influx_ctx, InfluxConfig::default()));
+
+    for id in 0..5 {
         let ctx = context.clone();
-        thread::spawn(move || server_worker(&ctx));
+        thread::spawn(move || server_worker(&ctx, id));
     }
     zmq::proxy(&frontend, &backend).expect("server failed proxying");
 }
 
 /// A server worker.
-/// 
+///
 /// Recevives messages from passed from the main server task and handles them
 /// in the appropriate manner.
-/// 
+///
 /// - `context` - the server backend context
-fn server_worker(context: &zmq::Context) {
+/// - `worker_id` - this worker's index, used to tell workers apart in traces
+fn server_worker(context: &zmq::Context, worker_id: usize) {
     let worker = context.socket(zmq::DEALER).unwrap();
     worker
         .connect("inproc://backend")
         .expect("worker failed to connect to backend");
+    let connection = Connection::new(worker, Some(SHARED_KEY));
+    let influx = metrics::connect_sink(context);
+    let mqtt_config = MqttConfig::default();
+    // One bridge (and one broker connection) per controller this worker has
+    // seen, since a controller's identity stays with whichever worker first
+    // handled one of its frames.
+    let mut bridges: HashMap<String, Bridge> = HashMap::new();
 
     loop {
-        let identity = worker
-            .recv_string(0)
-            .expect("worker failed receiving identity")
-            .unwrap();
-        let message = worker
-            .recv_bytes(0)
-            .expect("worker failed receiving message");
-
-        let mut log = identity.clone();
-        log.push_str(":\n");
-        for (i, chunk) in message.chunks(4).enumerate() {
-            log.push_str(format!("  sensor {}: {}\n", i, Sensor::from(chunk)).as_str());
+        let (identity, message) = match connection.recv_verified() {
+            Ok(verified) => verified,
+            Err(err) => {
+                tracing::error!("worker.id" = worker_id, %err, "dropped unverified frame");
+                continue;
+            }
+        };
+
+        let span = tracing::info_span!(
+            "controller",
+            "controller.id" = %identity,
+            "worker.id" = worker_id,
+        );
+        let _enter = span.enter();
+
+        match wire::decode_frame(&message) {
+            Ok(sensors) => {
+                let bridge = bridges
+                    .entry(identity.clone())
+                    .or_insert_with(|| Bridge::connect(&identity, &mqtt_config));
+
+                for (i, sensor) in sensors.iter().enumerate() {
+                    tracing::info!(
+                        zone = i,
+                        temperature = sensor.temperature.to_f32(),
+                        humidity = sensor.humidity.to_f32(),
+                        "reading",
+                    );
+                    metrics::push(&influx, Measurement::now(identity.clone(), i, *sensor));
+                    bridge.publish(i, sensor);
+                }
+
+                connection
+                    .send(&identity, b"R")
+                    .expect("worker failed sending message");
+            }
+            Err(err) => {
+                tracing::error!(%err, "dropped frame");
+                connection
+                    .send(&identity, b"E")
+                    .expect("worker failed sending message");
+            }
         }
-        println!("{}", log);
-
-        worker
-            .send(&identity, zmq::SNDMORE)
-            .expect("worker failed sending identity");
-        worker
-            .send("R", 0)
-            .expect("worker failed sending message");
     }
 }
 
 /// Runs the server.
+///
+/// Pass `--tracing` to install a structured subscriber filtered via
+/// `RUST_LOG` (`--tracing=json` for newline-delimited JSON output) in place
+/// of the default silent behavior.
 fn main() {
+    match std::env::args().find(|arg| arg.starts_with("--tracing")).as_deref() {
+        Some("--tracing=json") => iot_comm::core::telemetry::init(true),
+        Some("--tracing") => iot_comm::core::telemetry::init(false),
+        _ => {}
+    }
+
     server_task();
     loop {}
 }
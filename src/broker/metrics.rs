@@ -0,0 +1,157 @@
+/// InfluxDB line-protocol export.
+///
+/// A dedicated writer task binds an `inproc://influx` PULL socket; each
+/// server worker PUSHes decoded sensor readings to it as they're parsed, and
+/// the writer batches them into InfluxDB line protocol and flushes over HTTP
+/// whenever a batch fills up or a timer elapses. Keeping this off the hot
+/// path means a slow database can't stall sensor decoding.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use iot_comm::core::Sensor;
+
+/// Number of points to accumulate before flushing early.
+const BATCH_SIZE: usize = 100;
+
+/// Maximum time a batch sits before being flushed regardless of size.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// A single decoded reading, tagged with enough context to build a line
+/// protocol point.
+pub struct Measurement {
+    controller: String,
+    zone: usize,
+    sensor: Sensor,
+    timestamp_ns: u128,
+}
+
+impl Measurement {
+    /// Captures a measurement for `sensor` at `zone`, stamping it with the
+    /// current time. Timestamps are taken here, at decode time, not at flush
+    /// time, so a slow flush doesn't skew the recorded reading time.
+    pub fn now(controller: String, zone: usize, sensor: Sensor) -> Self {
+        let timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock before the epoch")
+            .as_nanos();
+        return Measurement { controller, zone, sensor, timestamp_ns };
+    }
+
+    /// Renders this measurement as an InfluxDB line protocol point.
+    fn to_line(&self) -> String {
+        return format!(
+            "sensor,controller={},zone={} temperature={},humidity={} {}",
+            escape_tag(&self.controller),
+            self.zone,
+            self.sensor.temperature.to_f32(),
+            self.sensor.humidity.to_f32(),
+            self.timestamp_ns,
+        );
+    }
+}
+
+/// Escapes spaces and commas in a tag value per the line protocol.
+fn escape_tag(value: &str) -> String {
+    return value.replace(',', "\\,").replace(' ', "\\ ");
+}
+
+/// Configuration for the Influx writer task.
+pub struct InfluxConfig {
+    pub url: String,
+    pub database: String,
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        return InfluxConfig {
+            url: "http://localhost:8086".to_owned(),
+            database: "iot_comm".to_owned(),
+        };
+    }
+}
+
+/// Binds `inproc://influx` and writes batches of points to InfluxDB as they
+/// arrive, flushing on `BATCH_SIZE` points or `FLUSH_INTERVAL`, whichever
+/// comes first.
+///
+/// - `context` - the server backend context, used to bind the PULL socket.
+/// - `config` - the InfluxDB HTTP endpoint and database to write to.
+pub fn influx_writer_task(context: &zmq::Context, config: InfluxConfig) {
+    let sink = context.socket(zmq::PULL).unwrap();
+    sink.set_rcvhwm(0).expect("failed setting influx sink high-water-mark");
+    sink.bind("inproc://influx").expect("failed binding influx sink");
+
+    let client = reqwest::blocking::Client::new();
+    let mut batch: Vec<String> = Vec::with_capacity(BATCH_SIZE);
+    let mut last_flush = Instant::now();
+
+    loop {
+        let remaining = FLUSH_INTERVAL.saturating_sub(last_flush.elapsed());
+        let timeout_ms = remaining.as_millis() as i64;
+        if sink.poll(zmq::POLLIN, timeout_ms).unwrap_or(0) > 0 {
+            if let Ok(bytes) = sink.recv_bytes(0) {
+                if let Ok(line) = String::from_utf8(bytes) {
+                    batch.push(line);
+                }
+            }
+        }
+
+        if batch.len() >= BATCH_SIZE || last_flush.elapsed() >= FLUSH_INTERVAL {
+            if !batch.is_empty() {
+                flush(&client, &config, &batch);
+                batch.clear();
+            }
+            last_flush = Instant::now();
+        }
+    }
+}
+
+/// Flushes a batch of line protocol points to InfluxDB over HTTP.
+fn flush(client: &reqwest::blocking::Client, config: &InfluxConfig, batch: &[String]) {
+    let url = format!("{}/write?db={}", config.url, config.database);
+    let body = batch.join("\n");
+    if let Err(err) = client.post(&url).body(body).send() {
+        eprintln!("influx writer failed flushing {} points: {}", batch.len(), err);
+    }
+}
+
+/// Connects a PUSH socket to the influx writer's PULL socket for a worker to
+/// send measurements on.
+pub fn connect_sink(context: &zmq::Context) -> zmq::Socket {
+    let sink = context.socket(zmq::PUSH).unwrap();
+    sink.set_sndhwm(0).expect("failed setting influx push high-water-mark");
+    sink.connect("inproc://influx").expect("failed connecting to influx sink");
+    return sink;
+}
+
+/// Pushes a measurement to the influx writer task. Drops the measurement,
+/// noting the drop on stderr, if the writer isn't reachable, since readings
+/// are still logged locally by the caller.
+pub fn push(sink: &zmq::Socket, measurement: Measurement) {
+    if sink.send(measurement.to_line(), zmq::DONTWAIT).is_err() {
+        eprintln!("dropped measurement for {} (influx sink unreachable)", measurement.controller);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_tag_escapes_commas_and_spaces() {
+        assert_eq!(escape_tag("plain"), "plain");
+        assert_eq!(escape_tag("room one"), "room\\ one");
+        assert_eq!(escape_tag("a,b c"), "a\\,b\\ c");
+    }
+
+    #[test]
+    fn to_line_renders_a_line_protocol_point() {
+        let sensor = Sensor::new();
+        let measurement = Measurement::now("ctrl one".to_owned(), 2, sensor);
+        let line = measurement.to_line();
+
+        assert!(line.starts_with("sensor,controller=ctrl\\ one,zone=2 "));
+        assert!(line.contains(&format!("temperature={}", sensor.temperature.to_f32())));
+        assert!(line.contains(&format!("humidity={}", sensor.humidity.to_f32())));
+    }
+}
@@ -0,0 +1,102 @@
+/// MQTT egress bridge.
+///
+/// Publishes decoded readings onto an MQTT broker under
+/// `<prefix>/<controller-id>/zone/<i>` as JSON, so downstream tools can
+/// subscribe without speaking ZMQ. A retained `<prefix>/<controller-id>/status`
+/// topic is set to `online` once connected, with a Last-Will of `offline`
+/// registered before connecting, so subscribers learn immediately when a
+/// controller's bridge drops.
+
+use std::thread;
+
+use iot_comm::core::Sensor;
+use rumqttc::{Client, LastWill, MqttOptions, QoS};
+use serde::Serialize;
+
+/// Configuration shared by every controller's bridge.
+pub struct MqttConfig {
+    pub broker_host: String,
+    pub broker_port: u16,
+    pub topic_prefix: String,
+    /// QoS for high-rate readings; `AtMostOnce` by default since a dropped
+    /// reading is superseded by the next one a few seconds later.
+    pub reading_qos: QoS,
+    /// QoS for the retained status topic; `AtLeastOnce` so liveness updates
+    /// aren't silently lost.
+    pub status_qos: QoS,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        return MqttConfig {
+            broker_host: "localhost".to_owned(),
+            broker_port: 1883,
+            topic_prefix: "iot-comm".to_owned(),
+            reading_qos: QoS::AtMostOnce,
+            status_qos: QoS::AtLeastOnce,
+        };
+    }
+}
+
+/// A reading as published to a controller's zone topic.
+#[derive(Serialize)]
+struct Reading {
+    temperature: f32,
+    humidity: f32,
+}
+
+/// A connection bridging one controller's readings onto the MQTT broker.
+pub struct Bridge {
+    client: Client,
+    topic_prefix: String,
+    controller_id: String,
+    reading_qos: QoS,
+}
+
+impl Bridge {
+    /// Connects to the broker for `controller_id`, registering the Last-Will
+    /// before connecting and publishing the retained `online` status once
+    /// the connection is up.
+    pub fn connect(controller_id: &str, config: &MqttConfig) -> Self {
+        let status_topic = format!("{}/{}/status", config.topic_prefix, controller_id);
+
+        let mut options = MqttOptions::new(controller_id, &config.broker_host, config.broker_port);
+        options.set_last_will(LastWill::new(
+            &status_topic,
+            "offline",
+            config.status_qos,
+            true,
+        ));
+
+        let (client, mut connection) = Client::new(options, 10);
+        // Drives the connection's event loop; the bridge only ever publishes,
+        // so incoming events are simply drained and discarded.
+        thread::spawn(move || for _ in connection.iter() {});
+
+        client
+            .publish(&status_topic, config.status_qos, true, "online")
+            .expect("failed publishing online status");
+
+        return Bridge {
+            client,
+            topic_prefix: config.topic_prefix.clone(),
+            controller_id: controller_id.to_owned(),
+            reading_qos: config.reading_qos,
+        };
+    }
+
+    /// Publishes a decoded reading to `<prefix>/<controller>/zone/<zone>` as
+    /// JSON.
+    pub fn publish(&self, zone: usize, sensor: &Sensor) {
+        let topic = format!("{}/{}/zone/{}", self.topic_prefix, self.controller_id, zone);
+        let payload = serde_json::to_vec(&Reading {
+            temperature: sensor.temperature.to_f32(),
+            humidity: sensor.humidity.to_f32(),
+        })
+        .expect("failed encoding reading as JSON");
+
+        if let Err(err) = self.client.publish(topic, self.reading_qos, false, payload) {
+            tracing::warn!("controller.id" = %self.controller_id, %err, "failed publishing reading");
+        }
+    }
+}
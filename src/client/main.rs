@@ -6,43 +6,108 @@
 
 use std::{thread, time};
 
+use iot_comm::core::auth::Connection;
+use iot_comm::core::discovery;
 use iot_comm::core::Controller;
 
+/// The endpoint used when mDNS discovery doesn't find a server in time.
+const FALLBACK_ENDPOINT: &str = "tcp://localhost:5570";
+
+/// How long a sensor's reading is cached before it's re-sampled.
+/// `sensor_data` only samples hardware that's gone stale, and only when it
+/// is called, so with this shorter than the 5s publish interval below every
+/// publish re-samples the sensors.
+const CACHE_DURATION: time::Duration = time::Duration::from_secs(1);
+
+/// How long to go without an ack before treating the connection as dead and
+/// re-resolving the server's endpoint. A DEALER socket reconnects to a dead
+/// peer silently and `poll` only errors on context termination, so a missing
+/// ack is the only liveness signal available. Set well above the 5s publish
+/// interval so a couple of acks lost to normal network jitter don't trigger
+/// a spurious reconnect.
+const ACK_TIMEOUT: time::Duration = time::Duration::from_secs(15);
 
 /// A controller client task
-/// 
+///
 /// Pushes the controller's sensor data to the server every 5 seconds via TCP,
 /// and prints any messages it receives from the server.
 fn controller_client_task() {
+    let mut controller = Controller::new().with_cache(CACHE_DURATION);
     let context = zmq::Context::new();
-    let client = context.socket(zmq::DEALER).unwrap();
-    let mut controller = Controller::new();
-
-    client.set_identity(controller.id.as_bytes())
-        .expect("failed setting client id");
-    client.connect("tcp://localhost:5570")
-        .expect("failed connecting client");
+    let mut endpoint = resolve_endpoint();
+    let mut connection = connect(&context, &controller, &endpoint);
+    let span = tracing::info_span!("controller", "controller.id" = %controller.id);
+    let _enter = span.enter();
+    let mut last_ack = time::Instant::now();
 
     // client task running loop
     loop {
-        if client.poll(zmq::POLLIN, 10).expect("client failed polling") > 0 {
-            let msg = client.recv_string(0)
-                .expect("client failed receivng response");
-            
-            println!("controller {}: {}", controller.id, &msg.unwrap());
+        match connection.poll(zmq::POLLIN, 10) {
+            Ok(n) if n > 0 => {
+                let msg = connection.recv_string()
+                    .expect("client failed receivng response");
+
+                tracing::info!(response = %msg, "received response");
+                last_ack = time::Instant::now();
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tracing::warn!(%err, "poll failed");
+            }
+        }
+
+        if last_ack.elapsed() >= ACK_TIMEOUT {
+            // No ack in ACK_TIMEOUT, most likely because the server
+            // restarted on a different host. Re-browse for it rather than
+            // retrying the now-stale cached address.
+            tracing::warn!(timeout = ?ACK_TIMEOUT, "no ack received, re-resolving endpoint");
+            endpoint = resolve_endpoint();
+            connection = connect(&context, &controller, &endpoint);
+            last_ack = time::Instant::now();
         }
 
-        client.send(controller.sensor_data(), 0)
+        let data = controller.sensor_data();
+        connection.send_signed(&controller.id, &data)
             .expect("client failed sending request");
-        
+
         thread::sleep(time::Duration::from_secs(5));
     }
 }
 
+/// Resolves the server's ROUTER endpoint via mDNS, falling back to
+/// `FALLBACK_ENDPOINT` if no responder answers in time.
+fn resolve_endpoint() -> String {
+    return discovery::resolve_endpoint().unwrap_or_else(|| FALLBACK_ENDPOINT.to_owned());
+}
+
+/// Connects a fresh DEALER socket identified as `controller` to `endpoint`,
+/// on `context`. Reuses the task's own context across reconnects rather than
+/// opening a new one per call, since a `zmq::Context` owns the I/O threads
+/// backing every socket created from it.
+fn connect(context: &zmq::Context, controller: &Controller, endpoint: &str) -> Connection {
+    let client = context.socket(zmq::DEALER).unwrap();
+
+    client.set_identity(controller.id.as_bytes())
+        .expect("failed setting client id");
+    client.connect(endpoint)
+        .expect("failed connecting client");
+
+    return Connection::new(client, controller.key());
+}
+
 /// Runs a number of clients.
-/// 
-/// Spawns a new thread for each controller client.
+///
+/// Spawns a new thread for each controller client. Pass `--tracing` to
+/// install a structured subscriber filtered via `RUST_LOG` (`--tracing=json`
+/// for newline-delimited JSON output), letting an operator isolate one
+/// misbehaving controller out of the thousand spawned here.
 fn main() {
+    match std::env::args().find(|arg| arg.starts_with("--tracing")).as_deref() {
+        Some("--tracing=json") => iot_comm::core::telemetry::init(true),
+        Some("--tracing") => iot_comm::core::telemetry::init(false),
+        _ => {}
+    }
+
     for i in 0..1000 {
         let builder = thread::Builder::new()
             .name(format!("controller {}", &i));
@@ -0,0 +1,215 @@
+/// HMAC-authenticated ZMQ connections.
+///
+/// `Connection` wraps a ZMQ socket together with an optional shared
+/// `hmac::Key`, mirroring the digest scheme Jupyter's kernel `Connection`
+/// uses to sign messages on its shell/iopub channels. When the key is
+/// present, frames sent with `send_signed` are tagged with an HMAC-SHA256
+/// signature and frames received with `recv_verified` are verified in
+/// constant time before they're handed back to the caller; `send` and
+/// `recv_string` carry unsigned replies such as acks. An empty/absent key disables
+/// signing entirely, which keeps today's unauthenticated behavior available
+/// for local testing.
+
+use core::fmt;
+
+use ring::hmac;
+
+/// An error produced while receiving and verifying a frame.
+#[derive(Debug)]
+pub enum AuthError {
+    /// The underlying ZMQ operation failed.
+    Zmq(zmq::Error),
+    /// The identity frame, or a reply read with `recv_string`, was not valid
+    /// UTF-8.
+    InvalidIdentity,
+    /// The HMAC tag did not match the frame.
+    InvalidSignature,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Zmq(err) => write!(f, "zmq error: {}", err),
+            AuthError::InvalidIdentity => write!(f, "identity or reply frame was not valid UTF-8"),
+            AuthError::InvalidSignature => write!(f, "HMAC signature verification failed"),
+        }
+    }
+}
+
+impl From<zmq::Error> for AuthError {
+    fn from(err: zmq::Error) -> Self {
+        return AuthError::Zmq(err);
+    }
+}
+
+/// A ZMQ socket paired with an optional HMAC key used to authenticate the
+/// frames sent and received over it.
+pub struct Connection {
+    socket: zmq::Socket,
+    key: Option<hmac::Key>,
+}
+
+impl Connection {
+    /// Wraps `socket` with an HMAC key built from `key_bytes`. A `None` or
+    /// empty key disables signing, matching the unauthenticated behavior of
+    /// the raw socket.
+    pub fn new(socket: zmq::Socket, key_bytes: Option<&[u8]>) -> Self {
+        let key = match key_bytes {
+            Some(bytes) if !bytes.is_empty() => Some(hmac::Key::new(hmac::HMAC_SHA256, bytes)),
+            _ => None,
+        };
+        return Connection { socket, key };
+    }
+
+    /// Signs `frame` (with `identity` folded into the MAC input so a captured
+    /// frame can't be replayed under a different identity) and sends it as
+    /// `[frame][mac]`. The socket's own ZMQ identity, if set, is what a
+    /// ROUTER peer will see prepended on the wire; it is not resent here.
+    pub fn send_signed(&self, identity: &str, frame: &[u8]) -> zmq::Result<()> {
+        let mac = self.sign(identity, frame);
+        self.socket.send(frame, zmq::SNDMORE)?;
+        return self.socket.send(mac, 0);
+    }
+
+    /// Receives a `[identity][frame][mac]` message as delivered by a ROUTER
+    /// frontend, verifies the MAC in constant time, and returns the identity
+    /// and frame. Returns `Err(AuthError::InvalidSignature)` without
+    /// processing the frame if verification fails.
+    pub fn recv_verified(&self) -> Result<(String, Vec<u8>), AuthError> {
+        let identity = self
+            .socket
+            .recv_string(0)?
+            .map_err(|_| AuthError::InvalidIdentity)?;
+        let frame = self.socket.recv_bytes(0)?;
+        let mac = self.socket.recv_bytes(0)?;
+
+        self.verify(&identity, &frame, &mac)?;
+        return Ok((identity, frame));
+    }
+
+    /// Sends an unsigned frame, for replies that don't need to be
+    /// authenticated (e.g. an ack back to a controller).
+    pub fn send(&self, identity: &str, frame: &[u8]) -> zmq::Result<()> {
+        self.socket.send(identity, zmq::SNDMORE)?;
+        return self.socket.send(frame, 0);
+    }
+
+    /// Polls the underlying socket. See `zmq::Socket::poll`.
+    pub fn poll(&self, events: zmq::PollEvents, timeout_ms: i64) -> zmq::Result<i32> {
+        return self.socket.poll(events, timeout_ms);
+    }
+
+    /// Receives a single unsigned string frame, for replies that don't carry
+    /// a MAC (e.g. an ack back to a controller).
+    pub fn recv_string(&self) -> Result<String, AuthError> {
+        return self
+            .socket
+            .recv_string(0)?
+            .map_err(|_| AuthError::InvalidIdentity);
+    }
+
+    fn sign(&self, identity: &str, frame: &[u8]) -> Vec<u8> {
+        return match &self.key {
+            Some(key) => hmac::sign(key, &Self::mac_input(identity, frame))
+                .as_ref()
+                .to_vec(),
+            None => Vec::new(),
+        };
+    }
+
+    fn verify(&self, identity: &str, frame: &[u8], mac: &[u8]) -> Result<(), AuthError> {
+        return match &self.key {
+            Some(key) => hmac::verify(key, &Self::mac_input(identity, frame), mac)
+                .map_err(|_| AuthError::InvalidSignature),
+            None => Ok(()),
+        };
+    }
+
+    fn mac_input(identity: &str, frame: &[u8]) -> Vec<u8> {
+        let mut input = Vec::with_capacity(identity.len() + frame.len());
+        input.extend_from_slice(identity.as_bytes());
+        input.extend_from_slice(frame);
+        return input;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"test-shared-key";
+
+    /// Builds an inproc PAIR socket for `connection` to send on and the
+    /// bare peer socket to read the raw frames back off of.
+    fn connected_pair(context: &zmq::Context, endpoint: &str) -> (Connection, zmq::Socket) {
+        let sender = context.socket(zmq::PAIR).unwrap();
+        sender.bind(endpoint).unwrap();
+        let receiver = context.socket(zmq::PAIR).unwrap();
+        receiver.connect(endpoint).unwrap();
+        return (Connection::new(sender, Some(KEY)), receiver);
+    }
+
+    #[test]
+    fn verifies_a_signature_it_produced() {
+        let context = zmq::Context::new();
+        let (connection, peer) = connected_pair(&context, "inproc://auth-round-trip");
+
+        connection.send_signed("controller-1", b"reading").unwrap();
+        let frame = peer.recv_bytes(0).unwrap();
+        let mac = peer.recv_bytes(0).unwrap();
+
+        let verifier = Connection::new(context.socket(zmq::PAIR).unwrap(), Some(KEY));
+        assert!(verifier.verify("controller-1", &frame, &mac).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_tampered_frame() {
+        let context = zmq::Context::new();
+        let (connection, peer) = connected_pair(&context, "inproc://auth-tamper");
+
+        connection.send_signed("controller-1", b"reading").unwrap();
+        let mut frame = peer.recv_bytes(0).unwrap();
+        let mac = peer.recv_bytes(0).unwrap();
+        frame[0] ^= 0xff;
+
+        let verifier = Connection::new(context.socket(zmq::PAIR).unwrap(), Some(KEY));
+        assert!(matches!(
+            verifier.verify("controller-1", &frame, &mac),
+            Err(AuthError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_replay_under_a_different_identity() {
+        let context = zmq::Context::new();
+        let (connection, peer) = connected_pair(&context, "inproc://auth-replay");
+
+        connection.send_signed("controller-1", b"reading").unwrap();
+        let frame = peer.recv_bytes(0).unwrap();
+        let mac = peer.recv_bytes(0).unwrap();
+
+        let verifier = Connection::new(context.socket(zmq::PAIR).unwrap(), Some(KEY));
+        assert!(matches!(
+            verifier.verify("controller-2", &frame, &mac),
+            Err(AuthError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn an_empty_key_disables_signing() {
+        let context = zmq::Context::new();
+        let endpoint = "inproc://auth-disabled";
+        let sender = context.socket(zmq::PAIR).unwrap();
+        sender.bind(endpoint).unwrap();
+        let peer = context.socket(zmq::PAIR).unwrap();
+        peer.connect(endpoint).unwrap();
+
+        let unauthenticated = Connection::new(sender, Some(&b""[..]));
+        unauthenticated.send_signed("controller-1", b"reading").unwrap();
+        let frame = peer.recv_bytes(0).unwrap();
+        let mac = peer.recv_bytes(0).unwrap();
+
+        assert!(mac.is_empty());
+        assert!(unauthenticated.verify("controller-1", &frame, &mac).is_ok());
+    }
+}
@@ -0,0 +1,112 @@
+/// mDNS/DNS-SD service discovery.
+///
+/// Lets the server advertise its ROUTER endpoint on the local network instead
+/// of controllers hardcoding `tcp://localhost:5570`, and lets controllers
+/// browse for that endpoint at startup. The server side is a `libmdns`
+/// responder; the client side browses with the `mdns` crate and reads the
+/// endpoint back out of the advertised TXT record.
+
+use std::net::{IpAddr, UdpSocket};
+use std::time::Duration;
+
+use futures_util::{pin_mut, stream::StreamExt};
+
+/// The DNS-SD service type controllers browse for and the server advertises.
+pub const SERVICE_TYPE: &str = "_iot-comm._tcp";
+
+/// The current discovery protocol version, advertised in the `version=` TXT
+/// record so a future-incompatible server can be told apart from this one.
+const PROTOCOL_VERSION: &str = "1";
+
+/// How long a re-announced service record stays valid before a responder
+/// should refresh it.
+pub const ANNOUNCE_TTL: Duration = Duration::from_secs(60);
+
+/// How long `resolve_endpoint` waits for a response before falling back to an
+/// explicit endpoint.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returns the local network address a peer would use to reach this host.
+///
+/// `0.0.0.0`/`*` is only valid as a bind address; advertising it as the
+/// connect endpoint makes every browsing controller try to connect to
+/// INADDR_ANY and fail. Connecting a UDP socket to an external address
+/// (without sending anything) makes the OS pick the outbound interface and
+/// its address, which is the address other hosts can actually reach us at.
+pub fn local_address() -> IpAddr {
+    let socket = UdpSocket::bind("0.0.0.0:0").expect("failed binding probe socket");
+    socket.connect("8.8.8.8:80").expect("failed routing probe socket");
+    return socket.local_addr().expect("failed reading probe socket address").ip();
+}
+
+/// Starts advertising the server's ROUTER endpoint over mDNS.
+///
+/// Registers `SERVICE_TYPE` on `port`, with a `endpoint=` TXT record callers
+/// can read back via `resolve_endpoint`, and a `version=` TXT record set to
+/// `PROTOCOL_VERSION`. Record TTLs and re-announcements follow `libmdns`'s
+/// own defaults; `ANNOUNCE_TTL` is not passed to the responder. Drop the
+/// returned responder to stop advertising.
+pub fn advertise(port: u16, endpoint: &str) -> libmdns::Responder {
+    let responder = libmdns::Responder::new().expect("failed starting mdns responder");
+    responder.register(
+        SERVICE_TYPE.to_owned(),
+        "iot-comm server".to_owned(),
+        port,
+        &[
+            &format!("endpoint={}", endpoint),
+            &format!("version={}", PROTOCOL_VERSION),
+        ],
+    );
+    return responder;
+}
+
+/// Browses for the first healthy `SERVICE_TYPE` instance on the local network
+/// and returns the `endpoint=` TXT value it advertises.
+///
+/// Returns `None` if nothing responds within `RESOLVE_TIMEOUT`, in which case
+/// the caller should fall back to an explicit endpoint argument.
+pub fn resolve_endpoint() -> Option<String> {
+    // libmdns registers services under the `.local` domain.
+    let service_name = format!("{}.local", SERVICE_TYPE);
+    let discovery = mdns::discover::all(&service_name, RESOLVE_TIMEOUT)
+        .expect("failed starting mdns discovery")
+        .listen();
+    pin_mut!(discovery);
+
+    let runtime = tokio::runtime::Runtime::new().expect("failed starting discovery runtime");
+    return runtime.block_on(async {
+        let first_endpoint = async {
+            while let Some(Ok(response)) = discovery.next().await {
+                if let Some(endpoint) = endpoint_from_response(&response) {
+                    return Some(endpoint);
+                }
+            }
+            return None;
+        };
+        return tokio::time::timeout(RESOLVE_TIMEOUT, first_endpoint)
+            .await
+            .unwrap_or(None);
+    });
+}
+
+/// Pulls the `endpoint=` TXT record out of a resolved mDNS response.
+fn endpoint_from_response(response: &mdns::Response) -> Option<String> {
+    for record in response.records() {
+        if let mdns::RecordKind::TXT(ref entries) = record.kind {
+            for entry in entries {
+                if let Some(endpoint) = entry.strip_prefix("endpoint=") {
+                    return Some(endpoint.to_owned());
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Returns the first resolved IP address in a response, if any. Kept around
+/// for callers that only need an address and not the full TXT-carried
+/// endpoint string.
+#[allow(dead_code)]
+fn ip_from_response(response: &mdns::Response) -> Option<IpAddr> {
+    return response.ip_addr();
+}
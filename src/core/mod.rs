@@ -4,10 +4,17 @@
 /// provides a common way to handle system data.
 
 use core::fmt;
+use std::time::{Duration, Instant};
+
 use half::f16;
 use rand::Rng;
 use nanoid::nanoid;
 
+pub mod auth;
+pub mod discovery;
+pub mod telemetry;
+pub mod wire;
+
 
 /// the bytes trait is implemented by structures with a custom byte
 /// represenation. Structures that implement this trait must also implement
@@ -18,38 +25,84 @@ trait Bytes<'a>: From<&'a [u8]> {
 }
 
 
+/// An error produced by a failed sensor read. Modeling reads as fallible lets
+/// a transient failure be retried immediately on the next call instead of
+/// being cached and served stale for the rest of the staleness window.
+#[derive(Debug)]
+pub struct ReadError;
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sensor read failed")
+    }
+}
+
 /// Sensor Structure.
-/// 
+///
 /// A structure representing sensor values.
 #[derive(Debug, Clone, Copy)]
 pub struct Sensor {
-    /// The temperature value in °C.
+    /// The last successfully read temperature value in °C.
     pub temperature: f16,
-    /// The humidity value in %.
+    /// The last successfully read humidity value in %.
     pub humidity: f16,
+    /// When `temperature`/`humidity` were last refreshed by a successful
+    /// `update`.
+    last_updated: Instant,
 }
 impl Sensor {
-    /// Creates a new sensor.
+    /// Creates a new sensor, seeded with an initial reading.
     pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        return Sensor {
-            temperature: f16::from_f32(rng.gen_range(40.0..50.0)),
-            humidity: f16::from_f32(rng.gen_range(10.0..20.0)),
-        }
+        let (temperature, humidity) = Self::poll().expect("initial sensor read failed");
+        return Sensor { temperature, humidity, last_updated: Instant::now() };
     }
-    /// Updates the sensor with the latest values.
-    pub fn update(&mut self) {
+
+    /// Polls the underlying sensor hardware for a fresh reading, without
+    /// touching the cache.
+    fn poll() -> Result<(f16, f16), ReadError> {
         let mut rng = rand::thread_rng();
-        self.temperature = f16::from_f32(rng.gen_range(40.0..50.0));
-        self.humidity = f16::from_f32(rng.gen_range(10.0..20.0));
+        return Ok((
+            f16::from_f32(rng.gen_range(40.0..50.0)),
+            f16::from_f32(rng.gen_range(10.0..20.0)),
+        ));
+    }
+
+    /// Updates the sensor with a fresh reading. Only a successful read
+    /// refreshes the cached values and `last_updated`; a failed read leaves
+    /// the existing cache in place so the caller keeps serving the last
+    /// known-good reading.
+    pub fn update(&mut self) -> Result<(), ReadError> {
+        let (temperature, humidity) = Self::poll()?;
+        self.temperature = temperature;
+        self.humidity = humidity;
+        self.last_updated = Instant::now();
+        return Ok(());
+    }
+
+    /// Returns whether the cached reading is older than `cache_duration` and
+    /// should be refreshed before it's served again.
+    fn is_stale(&self, cache_duration: Duration) -> bool {
+        return self.last_updated.elapsed() >= cache_duration;
     }
 }
 impl Bytes<'_> for Sensor {
     /// Returns the byte representation of the sensor. This byte representation
     /// is a vector of bytes of length `4`, such that the first 2 bytes
-    /// correspond to the temperature as a 16-bit float in native endian bytes,
-    /// and the last 2 bytes correspond to the humidity as a 16-bit float in 
-    /// native endian bytes.
+    /// correspond to the temperature as a 16-bit float in little endian bytes,
+    /// and the last 2 bytes correspond to the humidity as a 16-bit float in
+    /// little endian bytes. Little endian is used on the wire so readings can
+    /// be exchanged between controllers and servers of differing endianness.
+    ///
+    /// The `native-endian` feature switches this back to native-endian bytes
+    /// for loopback benchmarks where both ends of the socket share an
+    /// architecture and the conversion is pure overhead.
+    #[cfg(not(feature = "native-endian"))]
+    fn to_bytes(&self) -> Vec<u8> {
+        return [
+            self.temperature.to_le_bytes(),
+            self.humidity.to_le_bytes()
+        ].concat();
+    }
+    #[cfg(feature = "native-endian")]
     fn to_bytes(&self) -> Vec<u8> {
         return [
             self.temperature.to_ne_bytes(),
@@ -58,10 +111,20 @@ impl Bytes<'_> for Sensor {
     }
 }
 impl From<&[u8]> for Sensor {
+    #[cfg(not(feature = "native-endian"))]
+    fn from(bytes: &[u8]) -> Self {
+        return Sensor {
+            temperature: f16::from_le_bytes([bytes[0], bytes[1]]),
+            humidity: f16::from_le_bytes([bytes[2], bytes[3]]),
+            last_updated: Instant::now(),
+        }
+    }
+    #[cfg(feature = "native-endian")]
     fn from(bytes: &[u8]) -> Self {
         return Sensor {
             temperature: f16::from_ne_bytes([bytes[0], bytes[1]]),
             humidity: f16::from_ne_bytes([bytes[2], bytes[3]]),
+            last_updated: Instant::now(),
         }
     }
 }
@@ -84,30 +147,67 @@ pub struct Controller {
     pub id: String,
     /// The sensors connected to the unit.
     sensors: Vec<Sensor>,
+    /// The shared key used to HMAC-sign frames sent by this controller, if
+    /// any. A missing key leaves frames unsigned.
+    key: Option<Vec<u8>>,
+    /// How long a sensor's cached reading is served before `sensor_data`
+    /// polls it again. Zero (the default) polls every sensor on every call,
+    /// matching the original always-fresh behavior.
+    cache_duration: Duration,
 }
 impl Controller {
-    /// Creates a new controller.
+    /// Creates a new, unauthenticated controller with no read caching.
     pub fn new() -> Self {
         let mut sensors: Vec<Sensor> = Vec::new();
         for _ in 0..8 {
             sensors.push(Sensor::new());
         }
-        return Controller { id: nanoid!(), sensors: sensors };
+        return Controller {
+            id: nanoid!(),
+            sensors: sensors,
+            key: None,
+            cache_duration: Duration::ZERO,
+        };
     }
 
-    /// Returns the current readings of all sensors in byte format.
-    /// 
-    /// ### Format:
-    /// The sensor data format looks like the following:
-    /// |      | s_0     | s_1     | s_2     | s_3     | ... | s_n     |
-    /// |------|---------|---------|---------|---------|-----|---------|
-    /// | data | [u8; 4] | [u8; 4] | [u8; 4] | [u8; 4] | ... | [u8; 4] |
+    /// Creates a new controller that signs its frames with `key`.
+    pub fn with_key(key: Vec<u8>) -> Self {
+        let mut controller = Self::new();
+        controller.key = Some(key);
+        return controller;
+    }
+
+    /// Caches each sensor's reading for `cache_duration` before polling it
+    /// again, so sensors are polled at most once per `cache_duration` however
+    /// often `sensor_data` is called. Polling only happens inside
+    /// `sensor_data`, so a `cache_duration` shorter than the interval between
+    /// calls re-polls every sensor on every call.
+    pub fn with_cache(mut self, cache_duration: Duration) -> Self {
+        self.cache_duration = cache_duration;
+        return self;
+    }
+
+    /// Returns the controller's shared HMAC key, if it has one.
+    pub fn key(&self) -> Option<&[u8]> {
+        return self.key.as_deref();
+    }
+
+    /// Returns the current readings of all sensors as a versioned,
+    /// length-delimited wire frame. See `wire::encode_frame` for the frame
+    /// layout.
+    ///
+    /// Each sensor is only re-polled if its cached reading is older than
+    /// `cache_duration`; a failed poll logs and falls back to the existing
+    /// cache rather than failing the whole frame.
     pub fn sensor_data(&mut self) -> Vec<u8> {
-        let data: Vec<Vec<u8>> = self.sensors.iter_mut().map(|sensor| {
-            sensor.update();
-            return sensor.to_bytes();
-        }).collect();
-        return data.concat();
+        for sensor in self.sensors.iter_mut() {
+            if sensor.is_stale(self.cache_duration) {
+                if let Err(err) = sensor.update() {
+                    ::tracing::warn!("controller.id" = %self.id, %err, "serving cached reading");
+                }
+            }
+        }
+        return wire::encode_frame(&self.sensors);
     }
 }
 impl fmt::Display for Controller {
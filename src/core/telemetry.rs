@@ -0,0 +1,27 @@
+/// Structured tracing setup.
+///
+/// Exposes a single `init` function that installs a global `tracing`
+/// subscriber configured from `RUST_LOG`, so an operator can isolate one
+/// misbehaving controller out of the thousand spawned by the client binary
+/// instead of scrolling through unfilterable `println!` output. Call it once
+/// at the top of `main`, behind a `--tracing` flag so the demo still runs
+/// silently by default.
+
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global tracing subscriber.
+///
+/// Filtering is controlled by the `RUST_LOG` environment variable (e.g.
+/// `RUST_LOG=iot_comm=debug`), defaulting to `info` when unset. Set `json` to
+/// emit newline-delimited JSON events for ingestion by a log pipeline,
+/// instead of the human-readable default.
+pub fn init(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    if json {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
+}
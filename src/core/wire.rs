@@ -0,0 +1,199 @@
+/// Wire framing for sensor payloads.
+///
+/// This module defines the versioned, length-delimited frame that
+/// `Controller::sensor_data` produces and that `server_worker` parses back
+/// into individual `Sensor` readings. Multi-byte fields are little-endian so
+/// a frame means the same thing regardless of which architecture produced or
+/// consumed it.
+
+use core::fmt;
+
+use crate::core::{Bytes, Sensor};
+
+/// The current wire format version.
+///
+/// Bump this whenever the frame layout changes in an incompatible way.
+/// Readers must reject frames carrying a version they don't recognize rather
+/// than guessing at the layout.
+pub const WIRE_VERSION: u8 = 1;
+
+/// Size in bytes of a single encoded sensor reading.
+const SENSOR_SIZE: usize = 4;
+
+/// Size in bytes of the frame header (version + count).
+const HEADER_SIZE: usize = 3;
+
+/// An error produced while decoding a wire frame.
+#[derive(Debug)]
+pub enum FrameError {
+    /// The frame was too short to contain a full header or the number of
+    /// records its header claims.
+    Truncated,
+    /// The frame's version byte is not one this build understands.
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Truncated => write!(f, "frame is truncated"),
+            FrameError::UnsupportedVersion(version) => {
+                write!(f, "unsupported wire version: {}", version)
+            }
+        }
+    }
+}
+
+/// Encodes sensor readings into a versioned, length-delimited frame.
+///
+/// ### Format:
+/// |        | version | count       | s_0     | s_1     | ... | s_n     |
+/// |--------|---------|-------------|---------|---------|-----|---------|
+/// | data   | [u8; 1] | [u8; 2] LE  | [u8; 4] | [u8; 4] | ... | [u8; 4] |
+pub fn encode_frame(sensors: &[Sensor]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_SIZE + sensors.len() * SENSOR_SIZE);
+    frame.push(WIRE_VERSION);
+    frame.extend_from_slice(&(sensors.len() as u16).to_le_bytes());
+    for sensor in sensors {
+        frame.extend(sensor.to_bytes());
+    }
+    return frame;
+}
+
+/// Decodes a frame produced by `encode_frame` back into sensor readings.
+///
+/// Rejects frames with an unknown version or that are shorter than their
+/// header claims, rather than reading past the end of the buffer.
+pub fn decode_frame(frame: &[u8]) -> Result<Vec<Sensor>, FrameError> {
+    if frame.len() < HEADER_SIZE {
+        return Err(FrameError::Truncated);
+    }
+    let version = frame[0];
+    if version != WIRE_VERSION {
+        return Err(FrameError::UnsupportedVersion(version));
+    }
+
+    let count = u16::from_le_bytes([frame[1], frame[2]]) as usize;
+    let body = &frame[HEADER_SIZE..];
+    if body.len() < count * SENSOR_SIZE {
+        return Err(FrameError::Truncated);
+    }
+
+    return Ok(body.chunks(SENSOR_SIZE).take(count).map(Sensor::from).collect());
+}
+
+/// Schema-tolerant flexbuffers encoding, kept as an alternative to the fixed
+/// frame above.
+///
+/// Flexbuffers payloads carry their own schema, so a reader built against an
+/// older `Sensor` definition can still parse a frame that a newer writer
+/// produced with extra fields. Enabled with the `flexbuffers` feature; the
+/// fixed frame format is the default because it is smaller and has no
+/// external dependency.
+///
+/// This is deliberately a pair of frame-level functions (`Vec<Sensor>` in,
+/// `Vec<Sensor>` out) rather than a `Bytes`/`From<&[u8]>` implementation on
+/// `Sensor` itself. `Bytes::to_bytes` round-trips exactly one sensor at a
+/// time, which is right for the fixed layout above where records are just
+/// concatenated; flexbuffers' schema tolerance only pays for itself when the
+/// *whole* reading set is serialized as one self-describing document; doing
+/// that one `Sensor` at a time would mean a separate schema blob per record,
+/// which is far more overhead than the 4-byte fixed encoding it's meant to
+/// replace, and still wouldn't carry the record count or a version byte the
+/// way a real frame needs to.
+#[cfg(feature = "flexbuffers")]
+pub mod flex {
+    use std::time::Instant;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::core::Sensor;
+
+    /// Serializable mirror of `Sensor` used only for the flexbuffers path.
+    #[derive(Serialize, Deserialize)]
+    struct SensorRecord {
+        temperature: f32,
+        humidity: f32,
+    }
+
+    impl From<&Sensor> for SensorRecord {
+        fn from(sensor: &Sensor) -> Self {
+            SensorRecord {
+                temperature: sensor.temperature.to_f32(),
+                humidity: sensor.humidity.to_f32(),
+            }
+        }
+    }
+
+    /// Encodes sensor readings as a flexbuffers payload.
+    pub fn encode_frame(sensors: &[Sensor]) -> Vec<u8> {
+        let records: Vec<SensorRecord> = sensors.iter().map(SensorRecord::from).collect();
+        return flexbuffers::to_vec(&records).expect("failed to encode flexbuffers frame");
+    }
+
+    /// Decodes a flexbuffers payload produced by `encode_frame`.
+    pub fn decode_frame(frame: &[u8]) -> Result<Vec<Sensor>, flexbuffers::DeserializationError> {
+        let records: Vec<SensorRecord> = flexbuffers::from_slice(frame)?;
+        return Ok(records
+            .into_iter()
+            .map(|record| Sensor {
+                temperature: half::f16::from_f32(record.temperature),
+                humidity: half::f16::from_f32(record.humidity),
+                last_updated: Instant::now(),
+            })
+            .collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use half::f16;
+
+    use super::*;
+
+    fn sensor(temperature: f32, humidity: f32) -> Sensor {
+        return Sensor {
+            temperature: f16::from_f32(temperature),
+            humidity: f16::from_f32(humidity),
+            last_updated: Instant::now(),
+        };
+    }
+
+    #[test]
+    fn round_trips_through_encode_and_decode() {
+        let sensors = vec![sensor(21.5, 40.0), sensor(-3.0, 99.0)];
+        let frame = encode_frame(&sensors);
+        let decoded = decode_frame(&frame).expect("frame should decode");
+
+        assert_eq!(decoded.len(), sensors.len());
+        for (original, decoded) in sensors.iter().zip(decoded.iter()) {
+            assert_eq!(original.temperature.to_bits(), decoded.temperature.to_bits());
+            assert_eq!(original.humidity.to_bits(), decoded.humidity.to_bits());
+        }
+    }
+
+    #[test]
+    fn rejects_a_frame_shorter_than_its_header() {
+        let frame = [WIRE_VERSION, 0];
+        assert!(matches!(decode_frame(&frame), Err(FrameError::Truncated)));
+    }
+
+    #[test]
+    fn rejects_a_frame_shorter_than_its_claimed_count() {
+        let mut frame = encode_frame(&[sensor(21.5, 40.0), sensor(22.0, 41.0)]);
+        frame.truncate(frame.len() - 1);
+        assert!(matches!(decode_frame(&frame), Err(FrameError::Truncated)));
+    }
+
+    #[test]
+    fn rejects_an_unknown_version() {
+        let mut frame = encode_frame(&[sensor(21.5, 40.0)]);
+        frame[0] = WIRE_VERSION + 1;
+        assert!(matches!(
+            decode_frame(&frame),
+            Err(FrameError::UnsupportedVersion(v)) if v == WIRE_VERSION + 1
+        ));
+    }
+}